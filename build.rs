@@ -0,0 +1,411 @@
+//! Reads `instructions.in` and emits `instrs.rs` into `OUT_DIR`: the `Verb`
+//! enum, its `Display` impl, `Verb::to_bytes`, and (behind the `disasm`
+//! feature) `Verb::from_bytes`. See `instructions.in` for the table format.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Row {
+    variant: String,
+    form: Vec<String>,
+    byte0: Byte0Spec,
+    byte1: Option<u8>,
+    byte2: Vec<Byte2Item>,
+    imm: Option<usize>,
+    display: String,
+}
+
+enum Byte0Spec {
+    Literal([u8; 3]),
+    Fixed(u8),
+    NibbleReg(u8, usize),
+}
+
+enum Byte2Item {
+    RegHi(usize),
+    RegLo(usize),
+    Imm4Lo(usize),
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=instructions.in");
+
+    let src = fs::read_to_string("instructions.in").expect("failed to read instructions.in");
+    let rows = parse(&src);
+    let code = generate(&rows);
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("instrs.rs"), code).expect("failed to write instrs.rs");
+}
+
+fn parse_hex(s: &str) -> u8 {
+    u8::from_str_radix(s.trim().trim_start_matches("0x"), 16)
+        .unwrap_or_else(|_| panic!("not a hex byte: {}", s))
+}
+
+fn parse_byte0(s: &str) -> Byte0Spec {
+    if let Some(rest) = s.strip_prefix("LIT:") {
+        let bytes: Vec<u8> = rest.split(',').map(parse_hex).collect();
+        Byte0Spec::Literal([bytes[0], bytes[1], bytes[2]])
+    } else if let Some((hex, reg)) = s.split_once('+') {
+        let pos: usize = reg.trim_start_matches('r').parse().expect("byte0 reg slot");
+        Byte0Spec::NibbleReg(parse_hex(hex), pos)
+    } else {
+        Byte0Spec::Fixed(parse_hex(s))
+    }
+}
+
+fn parse_byte2(s: &str) -> Vec<Byte2Item> {
+    if s == "-" {
+        return Vec::new();
+    }
+    s.split(',')
+        .map(|item| {
+            let (name, nibble) = item.split_once(':').expect("byte2 item needs a nibble");
+            if let Some(pos) = name.trim().strip_prefix('r') {
+                let pos: usize = pos.parse().expect("byte2 reg slot");
+                match nibble.trim() {
+                    "hi" => Byte2Item::RegHi(pos),
+                    "lo" => Byte2Item::RegLo(pos),
+                    other => panic!("unknown nibble {}", other),
+                }
+            } else if let Some(pos) = name.trim().strip_prefix("imm") {
+                let pos: usize = pos.parse().expect("byte2 imm slot");
+                assert_eq!(nibble.trim(), "lo4", "only a lo4 immediate nibble is supported");
+                Byte2Item::Imm4Lo(pos)
+            } else {
+                panic!("unrecognized byte2 item: {}", item)
+            }
+        })
+        .collect()
+}
+
+fn parse(src: &str) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for line in src.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cols: Vec<&str> = line.split('|').map(|c| c.trim()).collect();
+        assert_eq!(cols.len(), 8, "malformed instructions.in row: {}", line);
+
+        let form = if cols[2] == "-" {
+            Vec::new()
+        } else {
+            cols[2].split(',').map(|s| s.trim().to_string()).collect()
+        };
+        let imm = if cols[6] == "-" {
+            None
+        } else {
+            Some(cols[6].parse().expect("imm column"))
+        };
+
+        rows.push(Row {
+            variant: cols[1].to_string(),
+            form,
+            byte0: parse_byte0(cols[3]),
+            byte1: if cols[4] == "-" { None } else { Some(parse_hex(cols[4])) },
+            byte2: parse_byte2(cols[5]),
+            imm,
+            display: cols[7].to_string(),
+        });
+    }
+    rows
+}
+
+/// The Rust variable an operand at 1-based position `pos` is bound to: a
+/// register operand becomes `r{pos}`, an immediate-valued one `imm{pos}`.
+fn var_name(form: &[String], pos: usize) -> String {
+    match form[pos - 1].as_str() {
+        "Reg" | "MemAtReg" => format!("r{}", pos),
+        "Imm" | "Imm4" | "MemAtImm" => format!("imm{}", pos),
+        other => panic!("unknown operand kind: {}", other),
+    }
+}
+
+fn operand_pattern(form: &[String], pos: usize) -> String {
+    let var = var_name(form, pos);
+    match form[pos - 1].as_str() {
+        "Reg" => format!("Operand::Reg({})", var),
+        "MemAtReg" => format!("Operand::MemAtReg({})", var),
+        "Imm" | "Imm4" => format!("Operand::Imm({})", var),
+        "MemAtImm" => format!("Operand::MemAtImm({})", var),
+        other => panic!("unknown operand kind: {}", other),
+    }
+}
+
+fn operand_pattern_tuple(row: &Row) -> String {
+    match row.form.len() {
+        0 => String::new(),
+        1 => operand_pattern(&row.form, 1),
+        2 => format!("({}, {})", operand_pattern(&row.form, 1), operand_pattern(&row.form, 2)),
+        n => panic!("unsupported operand arity: {}", n),
+    }
+}
+
+fn to_bytes_body(row: &Row) -> String {
+    let mut stmts = Vec::new();
+
+    match &row.byte0 {
+        Byte0Spec::Literal([a, b, c]) => {
+            stmts.push(format!("res[0] = {:#04X};", a));
+            stmts.push(format!("res[1] = {:#04X};", b));
+            stmts.push(format!("res[2] = {:#04X};", c));
+            return stmts.join("\n");
+        }
+        Byte0Spec::Fixed(v) => stmts.push(format!("res[0] = {:#04X};", v)),
+        Byte0Spec::NibbleReg(v, pos) => {
+            stmts.push(format!("res[0] = {:#04X};", v));
+            stmts.push(format!(
+                "{}.write_into_byte_lower(&mut res[0]);",
+                var_name(&row.form, *pos)
+            ));
+        }
+    }
+
+    if let Some(b1) = row.byte1 {
+        stmts.push(format!("res[1] = {:#04X};", b1));
+    }
+
+    for item in &row.byte2 {
+        match item {
+            Byte2Item::RegHi(pos) => stmts.push(format!(
+                "{}.write_into_byte_upper(&mut res[2]);",
+                var_name(&row.form, *pos)
+            )),
+            Byte2Item::RegLo(pos) => stmts.push(format!(
+                "{}.write_into_byte_lower(&mut res[2]);",
+                var_name(&row.form, *pos)
+            )),
+            Byte2Item::Imm4Lo(pos) => stmts.push(format!(
+                "write_imm_to_byte_lower(*{}, &mut res[2])?;",
+                var_name(&row.form, *pos)
+            )),
+        }
+    }
+
+    if let Some(pos) = row.imm {
+        stmts.push(format!(
+            "[res[1], res[2]] = {}.to_be_bytes();",
+            var_name(&row.form, pos)
+        ));
+    }
+
+    stmts.join("\n")
+}
+
+fn generate(rows: &[Row]) -> String {
+    let mut out = String::new();
+    out.push_str("// @generated by build.rs from instructions.in. Do not edit by hand.\n\n");
+
+    // Preserve first-seen order so the enum reads the same as the table.
+    let mut variant_order = Vec::new();
+    let mut variant_arity = BTreeMap::new();
+    let mut variant_display = BTreeMap::new();
+    for row in rows {
+        if !variant_arity.contains_key(&row.variant) {
+            variant_order.push(row.variant.clone());
+            variant_arity.insert(row.variant.clone(), row.form.len());
+            variant_display.insert(row.variant.clone(), row.display.clone());
+        }
+    }
+
+    out.push_str("#[derive(Debug, PartialEq, Eq, Clone)]\npub enum Verb {\n");
+    for variant in &variant_order {
+        match variant_arity[variant] {
+            0 => out.push_str(&format!("    {},\n", variant)),
+            1 => out.push_str(&format!("    {}(Operand),\n", variant)),
+            2 => out.push_str(&format!("    {}(Operand, Operand),\n", variant)),
+            n => panic!("unsupported operand arity: {}", n),
+        }
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("impl fmt::Display for Verb {\n    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {\n        match self {\n");
+    for variant in &variant_order {
+        let display = &variant_display[variant];
+        match variant_arity[variant] {
+            0 => out.push_str(&format!("            Verb::{} => write!(f, {}),\n", variant, display)),
+            1 => out.push_str(&format!(
+                "            Verb::{}(o1) => write!(f, {}, o1),\n",
+                variant, display
+            )),
+            2 => out.push_str(&format!(
+                "            Verb::{}(o1, o2) => write!(f, {}, o1, o2),\n",
+                variant, display
+            )),
+            n => panic!("unsupported operand arity: {}", n),
+        }
+    }
+    out.push_str("        }\n    }\n}\n\n");
+
+    // Group rows by variant so each arm can fall through to an "invalid
+    // operand combination" error for forms the table doesn't list.
+    let mut rows_by_variant: BTreeMap<&str, Vec<&Row>> = BTreeMap::new();
+    for row in rows {
+        rows_by_variant.entry(&row.variant).or_default().push(row);
+    }
+
+    out.push_str("impl Verb {\n    pub fn to_bytes(&self) -> Result<[u8; 3], EncodeError> {\n        let mut res = [0, 0, 0];\n\n        match self {\n");
+    for variant in &variant_order {
+        let variant_rows = &rows_by_variant[variant.as_str()];
+        match variant_arity[variant] {
+            0 => {
+                out.push_str(&format!("            Verb::{} => {{\n", variant));
+                out.push_str(&indent(&to_bytes_body(variant_rows[0]), 4));
+                out.push_str("\n            }\n");
+            }
+            1 => {
+                out.push_str(&format!("            Verb::{}(op1) => match op1 {{\n", variant));
+                for row in variant_rows {
+                    out.push_str(&format!("                {} => {{\n", operand_pattern_tuple(row)));
+                    out.push_str(&indent(&to_bytes_body(row), 5));
+                    out.push_str("\n                }\n");
+                }
+                out.push_str("                _ => return Err(self.invalid_operands(op1, None)),\n            },\n");
+            }
+            2 => {
+                out.push_str(&format!("            Verb::{}(op1, op2) => match (op1, op2) {{\n", variant));
+                for row in variant_rows {
+                    out.push_str(&format!("                {} => {{\n", operand_pattern_tuple(row)));
+                    out.push_str(&indent(&to_bytes_body(row), 5));
+                    out.push_str("\n                }\n");
+                }
+                out.push_str("                _ => return Err(self.invalid_operands(op1, Some(op2))),\n            },\n");
+            }
+            n => panic!("unsupported operand arity: {}", n),
+        }
+    }
+    out.push_str("        }\n        Ok(res)\n    }\n");
+    out.push_str(&generate_from_bytes_method(rows));
+    out.push_str("}\n\n");
+    out.push_str(&generate_decode_helpers(rows));
+
+    out
+}
+
+fn indent(body: &str, spaces: usize) -> String {
+    let pad = " ".repeat(spaces * 4);
+    body.lines().map(|l| format!("{}{}", pad, l)).collect::<Vec<_>>().join("\n")
+}
+
+/// Builds the `Operand::...(...)` reconstruction expression for the operand
+/// at 1-based position `pos`, the inverse of `operand_pattern`, reading it out
+/// of the raw `bytes`. `decode_expr` assembles these into a `Verb`.
+fn decode_operand(form: &[String], pos: usize, byte0: &Byte0Spec, byte2: &[Byte2Item]) -> String {
+    let is_reg = matches!(form[pos - 1].as_str(), "Reg" | "MemAtReg");
+
+    let reg_expr = if let Byte0Spec::NibbleReg(_, p) = byte0 {
+        if *p == pos {
+            Some("Reg::from_id(bytes[0])".to_string())
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    let reg_expr = reg_expr.or_else(|| {
+        byte2.iter().find_map(|item| match item {
+            Byte2Item::RegHi(p) if *p == pos => Some("Reg::from_id(bytes[2] >> 4)".to_string()),
+            Byte2Item::RegLo(p) if *p == pos => Some("Reg::from_id(bytes[2])".to_string()),
+            _ => None,
+        })
+    });
+
+    if is_reg {
+        let inner = reg_expr.unwrap_or_else(|| panic!("no register source for position {}", pos));
+        return match form[pos - 1].as_str() {
+            "Reg" => format!("Operand::Reg({})", inner),
+            "MemAtReg" => format!("Operand::MemAtReg({})", inner),
+            _ => unreachable!(),
+        };
+    }
+
+    let imm4 = byte2.iter().find_map(|item| match item {
+        Byte2Item::Imm4Lo(p) if *p == pos => Some("(bytes[2] & 0x0F) as u16".to_string()),
+        _ => None,
+    });
+    let inner = imm4.unwrap_or_else(|| "read_imm(&bytes)".to_string());
+    match form[pos - 1].as_str() {
+        "Imm" | "Imm4" => format!("Operand::Imm({})", inner),
+        "MemAtImm" => format!("Operand::MemAtImm({})", inner),
+        _ => unreachable!(),
+    }
+}
+
+fn decode_expr(row: &Row) -> String {
+    let args: Vec<String> = (1..=row.form.len())
+        .map(|pos| decode_operand(&row.form, pos, &row.byte0, &row.byte2))
+        .collect();
+    if args.is_empty() {
+        format!("Ok(Verb::{})", row.variant)
+    } else {
+        format!("Ok(Verb::{}({}))", row.variant, args.join(", "))
+    }
+}
+
+fn generate_from_bytes_method(rows: &[Row]) -> String {
+    let mut out = String::new();
+    out.push_str("\n    #[cfg(feature = \"disasm\")]\n");
+    out.push_str("    pub fn from_bytes(bytes: [u8; 3]) -> Result<Verb, DecodeError> {\n");
+
+    out.push_str("        match bytes {\n");
+    for row in rows {
+        if let Byte0Spec::Literal(lit) = &row.byte0 {
+            out.push_str(&format!(
+                "            [{:#04X}, {:#04X}, {:#04X}] => return Ok(Verb::{}),\n",
+                lit[0], lit[1], lit[2], row.variant
+            ));
+        }
+    }
+    out.push_str("            _ => {}\n        }\n\n");
+
+    // Fixed full-byte opcodes with no operand packed into byte0/byte2 besides
+    // a possible 16-bit immediate in bytes[1..3].
+    out.push_str("        match bytes[0] {\n");
+    for row in rows {
+        if let Byte0Spec::Fixed(v) = &row.byte0 {
+            if row.byte1.is_none() {
+                out.push_str(&format!("            {:#04X} => return {},\n", v, decode_expr(row)));
+            }
+        }
+    }
+    out.push_str("            0xF0 => return decode_f0(bytes),\n");
+    out.push_str("            _ => {}\n        }\n\n");
+
+    // Nibble-family opcodes: mask out the register id in the low nibble.
+    out.push_str("        match bytes[0] & 0xF0 {\n");
+    for row in rows {
+        if let Byte0Spec::NibbleReg(v, _) = &row.byte0 {
+            out.push_str(&format!("            {:#04X} => return {},\n", v, decode_expr(row)));
+        }
+    }
+    out.push_str("            _ => {}\n        }\n\n");
+
+    out.push_str("        Err(DecodeError::UnknownOpcode(bytes))\n    }\n");
+
+    out
+}
+
+/// Free helper functions used only by `from_bytes`, kept out of the `impl
+/// Verb` block since `decode_f0` dispatches across several variants.
+fn generate_decode_helpers(rows: &[Row]) -> String {
+    let mut out = String::new();
+
+    out.push_str("#[cfg(feature = \"disasm\")]\nfn decode_f0(bytes: [u8; 3]) -> Result<Verb, DecodeError> {\n    match bytes[1] {\n");
+    for row in rows {
+        if let Byte0Spec::Fixed(0xF0) = &row.byte0 {
+            if let Some(b1) = row.byte1 {
+                out.push_str(&format!("        {:#04X} => {},\n", b1, decode_expr(row)));
+            }
+        }
+    }
+    out.push_str("        _ => Err(DecodeError::UnknownOpcode(bytes)),\n    }\n}\n\n");
+
+    out.push_str("#[cfg(feature = \"disasm\")]\nfn read_imm(bytes: &[u8; 3]) -> u16 {\n    u16::from_be_bytes([bytes[1], bytes[2]])\n}\n");
+
+    out
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "disasm")]
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::instr_repr::{DecodeError, Verb};
+
+pub fn disassemble(bytes: &[u8]) -> Result<Vec<Verb>, DecodeError> {
+    if !bytes.len().is_multiple_of(3) {
+        return Err(DecodeError::TruncatedInput(bytes.len() % 3));
+    }
+
+    bytes
+        .chunks(3)
+        .map(|chunk| {
+            let mut buf = [0u8; 3];
+            buf.copy_from_slice(chunk);
+            Verb::from_bytes(buf)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instr_repr::{Operand, Reg};
+
+    #[test]
+    fn rejects_a_truncated_trailing_chunk() {
+        assert_eq!(disassemble(&[0x10]), Err(DecodeError::TruncatedInput(1)));
+    }
+
+    #[test]
+    fn decodes_a_whole_number_of_instructions() {
+        assert_eq!(
+            disassemble(&[0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF]),
+            Ok(vec![Verb::Nop, Verb::Halt])
+        );
+    }
+
+    #[test]
+    fn decodes_mov_reg_imm() {
+        assert_eq!(
+            disassemble(&[0x11, 0x00, 0x05]),
+            Ok(vec![Verb::Mov(Operand::Reg(Reg::R1), Operand::Imm(5))])
+        );
+    }
+}
@@ -1,29 +1,16 @@
+// `std` is on by default; the crate root gates `#![no_std]` on its absence
+// and declares `extern crate alloc`, leaving this module on `core` plus
+// `alloc` (always available as a sysroot crate once `no_std` applies) so the
+// encoder can be embedded in a firmware/on-target loader for this VM.
+#[cfg(feature = "std")]
 use std::fmt;
+#[cfg(not(feature = "std"))]
+use core::fmt;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Verb {
-    Mov(Operand, Operand),
-    Jmp(Operand),
-
-    Jz(Operand, Operand),
-    Jnz(Operand, Operand),
-
-    Add(Operand, Operand),
-    Sub(Operand, Operand),
-    And(Operand, Operand),
-    Or(Operand, Operand),
-    Not(Operand),
-    Shl(Operand, Operand),
-    Shr(Operand, Operand),
-
-    Call(Operand),
-    Ret,
-
-    Dbg(Operand),
-    DbgRegs,
-    Nop,
-    Halt,
-}
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::format;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Reg {
@@ -54,22 +41,41 @@ pub enum Operand {
     MemAtImm(u16),
 }
 
-impl Operand {
-    pub fn to_reg(&self) -> Reg {
-        match self {
-            Operand::Reg(r) => *r,
-            _ => panic!(),
-        }
-    }
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EncodeError {
+    InvalidOperandCombination {
+        verb: &'static str,
+        op1: Operand,
+        op2: Option<Operand>,
+    },
+    ImmediateTooLargeForNibble(u16),
+    UnresolvedLabel(String),
+}
 
-    pub fn to_imm(&self) -> u16 {
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Operand::Imm(imm) => *imm,
-            _ => panic!(),
+            EncodeError::InvalidOperandCombination { verb, op1, op2 } => match op2 {
+                Some(op2) => write!(f, "invalid operand combination for {}: {}, {}", verb, op1, op2),
+                None => write!(f, "invalid operand combination for {}: {}", verb, op1),
+            },
+            EncodeError::ImmediateTooLargeForNibble(v) => {
+                write!(f, "immediate 0x{:X} does not fit in 4 bits", v)
+            }
+            EncodeError::UnresolvedLabel(s) => write!(f, "unresolved label: {}", s),
         }
     }
 }
 
+#[cfg(feature = "disasm")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DecodeError {
+    UnknownOpcode([u8; 3]),
+    /// The byte stream's length isn't a multiple of 3, so its trailing chunk
+    /// can't be a whole instruction. Carries the number of leftover bytes.
+    TruncatedInput(usize),
+}
+
 impl Reg {
     pub fn to_id(&self) -> u8 {
         match self {
@@ -92,6 +98,27 @@ impl Reg {
         }
     }
 
+    pub fn from_id(id: u8) -> Reg {
+        match id & 0x0F {
+            0 => Reg::R0,
+            1 => Reg::R1,
+            2 => Reg::R2,
+            3 => Reg::R3,
+            4 => Reg::R4,
+            5 => Reg::R5,
+            6 => Reg::R6,
+            7 => Reg::R7,
+            8 => Reg::R8,
+            9 => Reg::R9,
+            10 => Reg::R10,
+            11 => Reg::R11,
+            12 => Reg::R12,
+            13 => Reg::R13,
+            14 => Reg::R14,
+            _ => Reg::R15,
+        }
+    }
+
     fn write_into_byte_lower(&self, b: &mut u8) {
         *b &= 0xF0;
         *b |= self.to_id();
@@ -121,189 +148,85 @@ impl fmt::Display for Operand {
     }
 }
 
-impl fmt::Display for Verb {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Verb::Mov(o1, o2) => write!(f, "mov {} {}", o1, o2),
-            Verb::Jmp(o1) => write!(f, "jmp {} ", o1),
-            Verb::Jz(o1, o2) => write!(f, "jz {} {}", o1, o2),
-            Verb::Jnz(o1, o2) => write!(f, "jnz {} {}", o1, o2),
-            Verb::Add(o1, o2) => write!(f, "add {} {}", o1, o2),
-            Verb::Sub(o1, o2) => write!(f, "sub {} {}", o1, o2),
-            Verb::And(o1, o2) => write!(f, "and {} {}", o1, o2),
-            Verb::Or(o1, o2) => write!(f, "or {} {}", o1, o2),
-            Verb::Not(o1) => write!(f, "not {}", o1),
-            Verb::Shl(o1, o2) => write!(f, "shl {} {}", o1, o2),
-            Verb::Shr(o1, o2) => write!(f, "shr {} {}", o1, o2),
-            Verb::Dbg(o1) => write!(f, "dbg {}", o1),
-            Verb::DbgRegs => write!(f, "dbg"),
-            Verb::Nop => write!(f, "nop"),
-            Verb::Halt => write!(f, "halt"),
+// `Verb`, its `Display` impl, and `Verb::to_bytes`/`Verb::from_bytes` are
+// generated from `instructions.in` by `build.rs` so the encoder and decoder
+// can never drift out of sync with each other. See `instructions.in` for the
+// table format and `build.rs` for the generator.
+include!(concat!(env!("OUT_DIR"), "/instrs.rs"));
 
-            Verb::Call(o1) => write!(f, "call {}", o1),
-            Verb::Ret => write!(f, "ret"),
+impl Verb {
+    fn invalid_operands(&self, op1: &Operand, op2: Option<&Operand>) -> EncodeError {
+        EncodeError::InvalidOperandCombination {
+            verb: self.name(),
+            op1: op1.clone(),
+            op2: op2.cloned(),
         }
     }
-}
-
-impl Verb {
-    pub fn to_bytes(&self) -> [u8; 3] {
-        let mut res = [0, 0, 0];
 
+    fn name(&self) -> &'static str {
         match self {
-            Verb::Mov(op1, op2) => match (op1, op2) {
-                (Operand::Reg(r1), Operand::Imm(imm)) => {
-                    res[0] = 0x10;
-                    r1.write_into_byte_lower(&mut res[0]);
-                    [res[1], res[2]] = imm.to_be_bytes();
-                }
-                (Operand::Reg(r1), Operand::MemAtImm(imm)) => {
-                    res[0] = 0x20;
-                    r1.write_into_byte_lower(&mut res[0]);
-                    [res[1], res[2]] = imm.to_be_bytes();
-                }
-                (Operand::MemAtImm(imm), Operand::Reg(r1)) => {
-                    res[0] = 0x30;
-                    r1.write_into_byte_lower(&mut res[0]);
-                    [res[1], res[2]] = imm.to_be_bytes();
-                }
-                (Operand::Reg(r1), Operand::Reg(r2)) => {
-                    res[0] = 0xF0;
-                    res[1] = 0x00;
-                    r1.write_into_byte_upper(&mut res[2]);
-                    r2.write_into_byte_lower(&mut res[2]);
-                }
-                (Operand::Reg(ra), Operand::MemAtReg(rb)) => {
-                    res[0] = 0xF0;
-                    res[1] = 0x01;
-                    ra.write_into_byte_upper(&mut res[2]);
-                    rb.write_into_byte_lower(&mut res[2]);
-                }
-                (Operand::MemAtReg(ra), Operand::Reg(rb)) => {
-                    res[0] = 0xF0;
-                    res[1] = 0x02;
-                    ra.write_into_byte_upper(&mut res[2]);
-                    rb.write_into_byte_lower(&mut res[2]);
-                }
-                _ => unreachable!(),
-            },
-
-            Verb::Jmp(operand) => {
-                res[0] = 0xE3;
-                [res[1], res[2]] = operand.to_imm().to_be_bytes();
-            }
-            Verb::Jz(imm, r) | Verb::Jnz(imm, r) => {
-                res[0] = match self {
-                    Verb::Jz(_, _) => 0x40,
-                    Verb::Jnz(_, _) => 0x50,
-                    _ => unreachable!(),
-                };
-                r.to_reg().write_into_byte_lower(&mut res[0]);
-                [res[1], res[2]] = imm.to_imm().to_be_bytes();
-            }
-
-            Verb::Add(op1, op2)
-            | Verb::Sub(op1, op2)
-            | Verb::And(op1, op2)
-            | Verb::Or(op1, op2) => match (op1, op2) {
-                (Operand::Reg(r1), Operand::Reg(r2)) => {
-                    res[0] = 0xF0;
-                    res[1] = match self {
-                        Verb::Add(..) => 0x20,
-                        Verb::Sub(..) => 0x21,
-                        Verb::And(..) => 0x22,
-                        Verb::Or(..) => 0x23,
-                        _ => unreachable!(),
-                    };
-                    r1.write_into_byte_upper(&mut res[2]);
-                    r2.write_into_byte_lower(&mut res[2]);
-                }
-                (Operand::Reg(r1), Operand::Imm(imm)) => {
-                    res[0] = match self {
-                        Verb::Add(..) => 0xA0,
-                        Verb::Sub(..) => 0xB0,
-                        Verb::And(..) => 0xC0,
-                        Verb::Or(..) => 0xD0,
-                        _ => unreachable!(),
-                    };
-                    r1.write_into_byte_lower(&mut res[0]);
-                    [res[1], res[2]] = imm.to_be_bytes();
-                }
-                _ => unreachable!(),
-            },
-
-            Verb::Not(r) => {
-                res[0] = 0xF0;
-                res[1] = 0x24;
-                r.to_reg().write_into_byte_upper(&mut res[2]);
-            }
-            Verb::Shl(op1, op2) | Verb::Shr(op1, op2) => match (op1, op2) {
-                (Operand::Reg(r1), Operand::Reg(r2)) => {
-                    res[0] = 0xF0;
-                    res[1] = match self {
-                        Verb::Shl(..) => 0x31,
-                        Verb::Shr(..) => 0x33,
-                        _ => unreachable!(),
-                    };
-                    r1.write_into_byte_upper(&mut res[2]);
-                    r2.write_into_byte_lower(&mut res[2]);
-                }
-                (Operand::Reg(r), Operand::Imm(imm)) => {
-                    res[0] = 0xF0;
-                    res[1] = match self {
-                        Verb::Shl(..) => 0x30,
-                        Verb::Shr(..) => 0x32,
-                        _ => unreachable!(),
-                    };
-                    r.write_into_byte_upper(&mut res[2]);
-                    write_imm_to_byte_lower(*imm, &mut res[2]);
-                }
-                _ => unreachable!(),
-            },
-
-            Verb::Dbg(op) => {
-                res[0] = 0xE0;
-                [res[1], res[2]] = op.to_imm().to_be_bytes();
-            }
-            Verb::DbgRegs => {
-                res[0] = 0xE1;
-            }
-            Verb::Nop => {
-                res[0] = 0x00;
-                res[1] = 0x00;
-                res[2] = 0x00;
-            }
-            Verb::Halt => {
-                res[0] = 0xFF;
-                res[1] = 0xFF;
-                res[2] = 0xFF;
-            }
-            Verb::Call(op) => {
-                res[0] = 0xE4;
-                [res[1], res[2]] = op.to_imm().to_be_bytes();
-            }
-            Verb::Ret => {
-                res[0] = 0xFF;
-                res[1] = 0xFF;
-                res[2] = 0xF0;
-            }
+            Verb::Mov(..) => "mov",
+            Verb::Jmp(..) => "jmp",
+            Verb::Jz(..) => "jz",
+            Verb::Jnz(..) => "jnz",
+            Verb::Add(..) => "add",
+            Verb::Sub(..) => "sub",
+            Verb::And(..) => "and",
+            Verb::Or(..) => "or",
+            Verb::Not(..) => "not",
+            Verb::Shl(..) => "shl",
+            Verb::Shr(..) => "shr",
+            Verb::Call(..) => "call",
+            Verb::Ret => "ret",
+            Verb::Dbg(..) => "dbg",
+            Verb::DbgRegs => "dbg",
+            Verb::Nop => "nop",
+            Verb::Halt => "halt",
         }
-        res
     }
 
-    pub fn as_hex_file_line(&self) -> String {
-        let bytes = self.to_bytes();
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    pub fn as_hex_file_line(&self) -> Result<String, EncodeError> {
+        let bytes = self.to_bytes()?;
 
         // format as hex, with padding to left
         // https://doc.rust-lang.org/std/fmt/
-        format!(
+        Ok(format!(
             "{:0>2X}_{:0>2X}_{:0>2X}  // {}",
             bytes[0], bytes[1], bytes[2], self
-        )
+        ))
     }
 }
 
-pub fn write_imm_to_byte_lower(imm: u16, b: &mut u8) {
+pub fn write_imm_to_byte_lower(imm: u16, b: &mut u8) -> Result<(), EncodeError> {
+    if imm > 0x0F {
+        return Err(EncodeError::ImmediateTooLargeForNibble(imm));
+    }
     *b &= 0xF0;
     *b |= imm.to_be_bytes()[1] & 0x0F;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_operand_combination_names_the_verb_and_operands() {
+        let err = Verb::Not(Operand::Imm(1)).to_bytes().unwrap_err();
+        assert_eq!(
+            err,
+            EncodeError::InvalidOperandCombination {
+                verb: "not",
+                op1: Operand::Imm(1),
+                op2: None,
+            }
+        );
+    }
+
+    #[test]
+    fn immediate_too_large_for_nibble_is_rejected() {
+        let err = Verb::Shl(Operand::Reg(Reg::R0), Operand::Imm(0x10)).to_bytes().unwrap_err();
+        assert_eq!(err, EncodeError::ImmediateTooLargeForNibble(0x10));
+    }
 }
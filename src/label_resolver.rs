@@ -1,8 +1,18 @@
-use std::collections::HashMap;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
-use crate::instr_repr::{Operand, Verb};
+use crate::instr_repr::{EncodeError, Operand, Verb};
+
+/// `lookup` resolves a label name to its address. Callers on `std` typically
+/// pass `|s| label_map.get(s).copied()` over a `HashMap`; this module has no
+/// opinion on how the map is built, which keeps it usable with `core`'s
+/// `no_std` under the `alloc`-only build.
+pub fn resolve_labels(
+    instrs: &mut Vec<Verb>,
+    lookup: impl Fn(&str) -> Option<u16>,
+) -> Result<(), Vec<EncodeError>> {
+    let mut errors = Vec::new();
 
-pub fn resolve_labels(instrs: &mut Vec<Verb>, label_map: &HashMap<String, u16>) {
     for verb in instrs {
         match verb {
             Verb::Jmp(operand)
@@ -10,11 +20,10 @@ pub fn resolve_labels(instrs: &mut Vec<Verb>, label_map: &HashMap<String, u16>)
             | Verb::Jnz(operand, _)
             | Verb::Call(operand) => {
                 if let Operand::Label(s) = operand {
-                    let optional_addr = label_map.get(s);
-                    if let Some(addr) = optional_addr {
-                        *operand = Operand::Imm(*addr);
+                    if let Some(addr) = lookup(s) {
+                        *operand = Operand::Imm(addr);
                     } else {
-                        panic!("unresolved label: {}", s);
+                        errors.push(EncodeError::UnresolvedLabel(s.clone()));
                     }
                 }
             }
@@ -22,4 +31,55 @@ pub fn resolve_labels(instrs: &mut Vec<Verb>, label_map: &HashMap<String, u16>)
             _ => {}
         }
     }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instr_repr::Reg;
+
+    #[test]
+    fn resolves_every_label_in_one_pass() {
+        let mut instrs = vec![
+            Verb::Jmp(Operand::Label("a".to_string())),
+            Verb::Call(Operand::Label("b".to_string())),
+        ];
+        let labels = [("a", 3u16), ("b", 9u16)];
+        resolve_labels(&mut instrs, |s| {
+            labels.iter().find(|(name, _)| *name == s).map(|(_, addr)| *addr)
+        })
+        .unwrap();
+
+        assert_eq!(
+            instrs,
+            vec![
+                Verb::Jmp(Operand::Imm(3)),
+                Verb::Call(Operand::Imm(9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn accumulates_every_unresolved_label_instead_of_stopping_at_the_first() {
+        let mut instrs = vec![
+            Verb::Jmp(Operand::Label("missing_a".to_string())),
+            Verb::Jz(Operand::Label("missing_b".to_string()), Operand::Reg(Reg::R0)),
+            Verb::Call(Operand::Label("defined".to_string())),
+        ];
+        let errors = resolve_labels(&mut instrs, |s| (s == "defined").then_some(0)).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                EncodeError::UnresolvedLabel("missing_a".to_string()),
+                EncodeError::UnresolvedLabel("missing_b".to_string()),
+            ]
+        );
+    }
 }
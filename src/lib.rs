@@ -0,0 +1,22 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A tiny 3-byte-instruction assembler/emulator. `instr_repr` defines the
+//! `Verb` instruction set (generated from `instructions.in`), `label_resolver`
+//! patches label operands into resolved addresses, and `disasm` (behind the
+//! `disasm` feature) turns machine code back into `Verb`s; all three are
+//! `no_std` (plus `alloc`) compatible for embedding in a firmware/on-target
+//! loader. `macro_expander` expands user-defined instruction templates ahead
+//! of label resolution; it's a host-tooling concern built on `std`'s
+//! `HashMap` and requires the `std` feature.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+pub mod instr_repr;
+pub mod label_resolver;
+
+#[cfg(feature = "std")]
+pub mod macro_expander;
+
+#[cfg(feature = "disasm")]
+pub mod disasm;
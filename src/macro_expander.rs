@@ -0,0 +1,289 @@
+//! Expands macro invocations into a flat `Verb` stream before label
+//! resolution. Host-tooling only (built on `std::collections::HashMap`), so
+//! the crate gates this module behind the `std` feature rather than the
+//! `no_std`-friendly `alloc` path `instr_repr`/`label_resolver`/`disasm` use.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::instr_repr::{Operand, Verb};
+
+/// A parameterized template: a macro invocation is replaced by `body` with
+/// each `Operand::Label` matching an entry of `params` substituted for the
+/// corresponding argument, and every other label uniquely renamed so that
+/// repeated expansions of the same macro don't collide.
+#[derive(Debug, Clone)]
+pub struct MacroDef {
+    pub params: Vec<String>,
+    pub body: Vec<Item>,
+}
+
+/// One entry of the pre-expansion instruction stream: a plain instruction, a
+/// label definition marking the address of whatever follows it, or a macro
+/// invocation to be expanded in place.
+#[derive(Debug, Clone)]
+pub enum Item {
+    Instr(Verb),
+    Label(String),
+    Call(String, Vec<Operand>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MacroError {
+    UndefinedMacro(String),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    RecursiveMacro(String),
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::UndefinedMacro(name) => write!(f, "undefined macro: {}", name),
+            MacroError::ArityMismatch { name, expected, found } => write!(
+                f,
+                "macro {} expects {} argument(s), got {}",
+                name, expected, found
+            ),
+            MacroError::RecursiveMacro(name) => write!(f, "recursive macro call: {}", name),
+        }
+    }
+}
+
+/// Expands `items` into a flat instruction stream plus the byte addresses of
+/// any labels defined along the way (including macro-local labels, which
+/// exist only after expansion). Merge the returned map into the label table
+/// passed to [`crate::label_resolver::resolve_labels`].
+pub fn expand_macros(
+    items: &[Item],
+    macros: &HashMap<String, MacroDef>,
+) -> Result<(Vec<Verb>, HashMap<String, u16>), MacroError> {
+    let mut counter = 0;
+    let mut out = Vec::new();
+    let mut labels = HashMap::new();
+    let mut call_stack = Vec::new();
+
+    for item in items {
+        expand_item(item, macros, &mut call_stack, &mut counter, &mut out, &mut labels)?;
+    }
+
+    Ok((out, labels))
+}
+
+fn expand_item(
+    item: &Item,
+    macros: &HashMap<String, MacroDef>,
+    call_stack: &mut Vec<String>,
+    counter: &mut u32,
+    out: &mut Vec<Verb>,
+    labels: &mut HashMap<String, u16>,
+) -> Result<(), MacroError> {
+    match item {
+        Item::Instr(verb) => {
+            out.push(verb.clone());
+            Ok(())
+        }
+        Item::Label(name) => {
+            labels.insert(name.clone(), out.len() as u16 * 3);
+            Ok(())
+        }
+        Item::Call(name, args) => expand_call(name, args, macros, call_stack, counter, out, labels),
+    }
+}
+
+fn expand_call(
+    name: &str,
+    args: &[Operand],
+    macros: &HashMap<String, MacroDef>,
+    call_stack: &mut Vec<String>,
+    counter: &mut u32,
+    out: &mut Vec<Verb>,
+    labels: &mut HashMap<String, u16>,
+) -> Result<(), MacroError> {
+    if call_stack.iter().any(|caller| caller == name) {
+        return Err(MacroError::RecursiveMacro(name.to_string()));
+    }
+
+    let def = macros
+        .get(name)
+        .ok_or_else(|| MacroError::UndefinedMacro(name.to_string()))?;
+    if def.params.len() != args.len() {
+        return Err(MacroError::ArityMismatch {
+            name: name.to_string(),
+            expected: def.params.len(),
+            found: args.len(),
+        });
+    }
+
+    *counter += 1;
+    let invocation = *counter;
+    let bindings: HashMap<&str, &Operand> =
+        def.params.iter().map(String::as_str).zip(args.iter()).collect();
+
+    call_stack.push(name.to_string());
+    for body_item in &def.body {
+        let renamed = match body_item {
+            Item::Instr(verb) => Item::Instr(map_operands(verb, &|op| {
+                substitute_operand(op, &bindings, invocation)
+            })),
+            Item::Label(label_name) => Item::Label(rename_local_label(label_name, invocation)),
+            Item::Call(callee, callee_args) => Item::Call(
+                callee.clone(),
+                callee_args
+                    .iter()
+                    .map(|op| substitute_operand(op, &bindings, invocation))
+                    .collect(),
+            ),
+        };
+        expand_item(&renamed, macros, call_stack, counter, out, labels)?;
+    }
+    call_stack.pop();
+
+    Ok(())
+}
+
+/// Macro-local labels (definitions and references alike) get suffixed with
+/// the invocation count so two expansions of the same macro never collide.
+fn rename_local_label(name: &str, invocation: u32) -> String {
+    format!("{}__{}", name, invocation)
+}
+
+/// A label that names a macro parameter is bound to the caller's argument;
+/// any other label is macro-local and gets uniquely renamed by
+/// [`rename_local_label`].
+fn substitute_operand(op: &Operand, bindings: &HashMap<&str, &Operand>, invocation: u32) -> Operand {
+    match op {
+        Operand::Label(name) => match bindings.get(name.as_str()) {
+            Some(bound) => (*bound).clone(),
+            None => Operand::Label(rename_local_label(name, invocation)),
+        },
+        other => other.clone(),
+    }
+}
+
+fn map_operands(verb: &Verb, f: &impl Fn(&Operand) -> Operand) -> Verb {
+    match verb {
+        Verb::Mov(o1, o2) => Verb::Mov(f(o1), f(o2)),
+        Verb::Jmp(o1) => Verb::Jmp(f(o1)),
+        Verb::Jz(o1, o2) => Verb::Jz(f(o1), f(o2)),
+        Verb::Jnz(o1, o2) => Verb::Jnz(f(o1), f(o2)),
+        Verb::Add(o1, o2) => Verb::Add(f(o1), f(o2)),
+        Verb::Sub(o1, o2) => Verb::Sub(f(o1), f(o2)),
+        Verb::And(o1, o2) => Verb::And(f(o1), f(o2)),
+        Verb::Or(o1, o2) => Verb::Or(f(o1), f(o2)),
+        Verb::Not(o1) => Verb::Not(f(o1)),
+        Verb::Shl(o1, o2) => Verb::Shl(f(o1), f(o2)),
+        Verb::Shr(o1, o2) => Verb::Shr(f(o1), f(o2)),
+        Verb::Call(o1) => Verb::Call(f(o1)),
+        Verb::Ret => Verb::Ret,
+        Verb::Dbg(o1) => Verb::Dbg(f(o1)),
+        Verb::DbgRegs => Verb::DbgRegs,
+        Verb::Nop => Verb::Nop,
+        Verb::Halt => Verb::Halt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instr_repr::Reg;
+    use crate::label_resolver::resolve_labels;
+
+    fn macros_with(name: &str, def: MacroDef) -> HashMap<String, MacroDef> {
+        let mut macros = HashMap::new();
+        macros.insert(name.to_string(), def);
+        macros
+    }
+
+    #[test]
+    fn undefined_macro_is_an_error() {
+        let items = vec![Item::Call("missing".to_string(), vec![])];
+        let err = expand_macros(&items, &HashMap::new()).unwrap_err();
+        assert_eq!(err, MacroError::UndefinedMacro("missing".to_string()));
+    }
+
+    #[test]
+    fn arity_mismatch_is_an_error() {
+        let macros = macros_with(
+            "one_arg",
+            MacroDef {
+                params: vec!["x".to_string()],
+                body: vec![],
+            },
+        );
+        let items = vec![Item::Call("one_arg".to_string(), vec![])];
+        let err = expand_macros(&items, &macros).unwrap_err();
+        assert_eq!(
+            err,
+            MacroError::ArityMismatch {
+                name: "one_arg".to_string(),
+                expected: 1,
+                found: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn self_recursive_macro_is_an_error() {
+        let macros = macros_with(
+            "loopy",
+            MacroDef {
+                params: vec![],
+                body: vec![Item::Call("loopy".to_string(), vec![])],
+            },
+        );
+        let items = vec![Item::Call("loopy".to_string(), vec![])];
+        let err = expand_macros(&items, &macros).unwrap_err();
+        assert_eq!(err, MacroError::RecursiveMacro("loopy".to_string()));
+    }
+
+    /// A macro expanding a bounded `do { counter -= 1 } while (counter != 0)`
+    /// loop needs to both define and jump back to an internal label; the
+    /// label definition only gets an address once expansion places it in the
+    /// output stream, so the caller must merge `expand_macros`'s returned
+    /// label map into the one it hands to `resolve_labels`.
+    #[test]
+    fn loop_macro_defines_and_resolves_its_own_label() {
+        let macros = macros_with(
+            "count_down",
+            MacroDef {
+                params: vec!["counter".to_string()],
+                body: vec![
+                    Item::Label("loop_start".to_string()),
+                    Item::Instr(Verb::Sub(
+                        Operand::Label("counter".to_string()),
+                        Operand::Imm(1),
+                    )),
+                    Item::Instr(Verb::Jnz(
+                        Operand::Label("loop_start".to_string()),
+                        Operand::Label("counter".to_string()),
+                    )),
+                ],
+            },
+        );
+
+        let items = vec![
+            Item::Instr(Verb::Mov(Operand::Reg(Reg::R1), Operand::Imm(3))),
+            Item::Call("count_down".to_string(), vec![Operand::Reg(Reg::R1)]),
+            Item::Instr(Verb::Halt),
+        ];
+
+        let (mut verbs, labels) = expand_macros(&items, &macros).unwrap();
+        assert_eq!(labels.get("loop_start__1"), Some(&3));
+
+        resolve_labels(&mut verbs, |s| labels.get(s).copied()).unwrap();
+
+        assert_eq!(
+            verbs,
+            vec![
+                Verb::Mov(Operand::Reg(Reg::R1), Operand::Imm(3)),
+                Verb::Sub(Operand::Reg(Reg::R1), Operand::Imm(1)),
+                Verb::Jnz(Operand::Imm(3), Operand::Reg(Reg::R1)),
+                Verb::Halt,
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Property tests for the `instructions.in`-generated encoder/decoder pair.
+//! Requires the `disasm` feature, which is what exposes `Verb::from_bytes`.
+
+#![cfg(feature = "disasm")]
+
+use asm_emu::instr_repr::{Operand, Reg, Verb};
+use proptest::prelude::*;
+
+fn reg() -> impl Strategy<Value = Reg> {
+    (0u8..16).prop_map(Reg::from_id)
+}
+
+fn reg_operand() -> impl Strategy<Value = Operand> {
+    reg().prop_map(Operand::Reg)
+}
+
+fn mem_at_reg_operand() -> impl Strategy<Value = Operand> {
+    reg().prop_map(Operand::MemAtReg)
+}
+
+fn imm_operand() -> impl Strategy<Value = Operand> {
+    any::<u16>().prop_map(Operand::Imm)
+}
+
+fn mem_at_imm_operand() -> impl Strategy<Value = Operand> {
+    any::<u16>().prop_map(Operand::MemAtImm)
+}
+
+/// A 4-bit immediate, the only kind `Shl`/`Shr` accept in their
+/// register-and-immediate form.
+fn imm4_operand() -> impl Strategy<Value = Operand> {
+    (0u16..16).prop_map(Operand::Imm)
+}
+
+/// One `Strategy` per row of `instructions.in`, so this generator can never
+/// produce an operand combination `to_bytes` would reject.
+fn verb() -> impl Strategy<Value = Verb> {
+    prop_oneof![
+        (reg_operand(), imm_operand()).prop_map(|(r, v)| Verb::Mov(r, v)),
+        (reg_operand(), mem_at_imm_operand()).prop_map(|(r, v)| Verb::Mov(r, v)),
+        (mem_at_imm_operand(), reg_operand()).prop_map(|(v, r)| Verb::Mov(v, r)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Mov(a, b)),
+        (reg_operand(), mem_at_reg_operand()).prop_map(|(a, b)| Verb::Mov(a, b)),
+        (mem_at_reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Mov(a, b)),
+        imm_operand().prop_map(Verb::Jmp),
+        (imm_operand(), reg_operand()).prop_map(|(i, r)| Verb::Jz(i, r)),
+        (imm_operand(), reg_operand()).prop_map(|(i, r)| Verb::Jnz(i, r)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Add(a, b)),
+        (reg_operand(), imm_operand()).prop_map(|(a, b)| Verb::Add(a, b)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Sub(a, b)),
+        (reg_operand(), imm_operand()).prop_map(|(a, b)| Verb::Sub(a, b)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::And(a, b)),
+        (reg_operand(), imm_operand()).prop_map(|(a, b)| Verb::And(a, b)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Or(a, b)),
+        (reg_operand(), imm_operand()).prop_map(|(a, b)| Verb::Or(a, b)),
+        reg_operand().prop_map(Verb::Not),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Shl(a, b)),
+        (reg_operand(), imm4_operand()).prop_map(|(a, b)| Verb::Shl(a, b)),
+        (reg_operand(), reg_operand()).prop_map(|(a, b)| Verb::Shr(a, b)),
+        (reg_operand(), imm4_operand()).prop_map(|(a, b)| Verb::Shr(a, b)),
+        imm_operand().prop_map(Verb::Call),
+        Just(Verb::Ret),
+        imm_operand().prop_map(Verb::Dbg),
+        Just(Verb::DbgRegs),
+        Just(Verb::Nop),
+        Just(Verb::Halt),
+    ]
+}
+
+proptest! {
+    /// Every `Verb` this generator can produce must survive an
+    /// encode/decode round trip; a mismatch means `to_bytes` and
+    /// `from_bytes` have drifted out of sync with `instructions.in`.
+    #[test]
+    fn round_trip(v in verb()) {
+        let bytes = v.to_bytes().expect("generator only produces valid operand combinations");
+        prop_assert_eq!(Verb::from_bytes(bytes).unwrap(), v);
+    }
+
+    /// `from_bytes` must never panic on arbitrary input. When it does
+    /// decode something, re-encoding it must decode back to the same
+    /// `Verb`; for the fully-literal opcodes (`ret`/`nop`/`halt`), which
+    /// have no "don't care" bits to lose, it must reproduce the exact
+    /// original bytes too.
+    #[test]
+    fn fuzz_decode_never_panics(bytes in any::<[u8; 3]>()) {
+        if let Ok(v) = Verb::from_bytes(bytes) {
+            let re_encoded = v.to_bytes().expect("anything from_bytes produces must re-encode");
+            let is_fully_literal = matches!(v, Verb::Ret | Verb::Nop | Verb::Halt);
+            prop_assert_eq!(Verb::from_bytes(re_encoded).unwrap(), v);
+
+            if is_fully_literal {
+                prop_assert_eq!(re_encoded, bytes);
+            }
+        }
+    }
+}